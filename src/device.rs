@@ -0,0 +1,81 @@
+use libc::c_int;
+
+use std::task::spawn;
+
+use libnanomsg;
+use result::{NanoResult, NanoError};
+use result::{DeviceError};
+use Socket;
+
+/// A forwarder that relays messages between two sockets, the way
+/// nanomsg's own device concept (and the old Ruby binding's
+/// `run_device`/`run_loopback`) does. `nn_device` blocks the calling task
+/// until one of the underlying sockets is closed, or until `terminate` is
+/// called to unblock every nanomsg call in the process, so `Device` runs
+/// it on its own task and hands the eventual result back over a channel.
+pub struct Device {
+    receiver: Receiver<NanoResult<()>>
+}
+
+impl Device {
+
+    /// Forward messages bidirectionally between `s1` and `s2`, typically
+    /// two sockets of complementary protocols (e.g. a `Pull` front end and
+    /// a `Push` back end acting as a load-balancing relay).
+    pub fn new(s1: &Socket, s2: &Socket) -> Device {
+        Device::run_device(s1.socket, s2.socket)
+    }
+
+    /// A loopback device: forward `s` back onto itself, which is useful
+    /// for testing or for terminating one end of a longer chain.
+    pub fn loopback(s: &Socket) -> Device {
+        Device::run_device(s.socket, -1)
+    }
+
+    /// Spawn the task that actually blocks in `nn_device`. Named apart
+    /// from `std::task::spawn` so the two aren't confused for each other.
+    fn run_device(fd1: c_int, fd2: c_int) -> Device {
+        let (tx, rx) = channel();
+
+        spawn(proc() {
+            // nn_device only ever returns once it has failed: it blocks
+            // forever relaying messages until one of its sockets is closed
+            // or nn_term() is called, then returns -1 with errno set to
+            // why it stopped. ETERM is that normal, requested shutdown, so
+            // it is reported as Ok(()) here rather than as a failure.
+            let ret = unsafe { libnanomsg::nn_device(fd1, fd2) };
+
+            let result = if ret == -1 {
+                let errno = unsafe { libnanomsg::nn_errno() };
+
+                if errno == libnanomsg::ETERM {
+                    Ok(())
+                } else {
+                    Err(NanoError::from_nn_errno(DeviceError))
+                }
+            } else {
+                Ok(())
+            };
+
+            tx.send(result);
+        });
+
+        Device { receiver: rx }
+    }
+
+    /// Block until the device's task terminates and report how it ended:
+    /// `Ok(())` if it was stopped by a process-wide call to `terminate`
+    /// (`ETERM`), `Err` carrying the error that stopped `nn_device` for any
+    /// other reason, including one of its sockets being closed.
+    pub fn wait(self) -> NanoResult<()> {
+        self.receiver.recv()
+    }
+}
+
+/// Unblock every nanomsg call currently blocked anywhere in this process
+/// (not just this `Device` — `nn_term` has no notion of a single device or
+/// socket), so a blocked device can be interrupted cleanly instead of the
+/// process being killed out from under it.
+pub fn terminate() {
+    unsafe { libnanomsg::nn_term(); }
+}
@@ -0,0 +1,50 @@
+use libc::c_int;
+
+use libnanomsg;
+use result::{NanoResult, NanoError};
+use result::{EndpointShutdownError};
+
+/// A single binding or connection created by `Socket::bind` or
+/// `Socket::connect`. A `Socket` can hold several endpoints across
+/// different transports at once; each one can be torn down on its own
+/// with `shutdown` without affecting the others or the socket itself.
+///
+/// `Endpoint` holds a copy of the socket's raw file descriptor rather than
+/// borrowing the `Socket`, so nothing stops it from outliving the socket
+/// it came from. See `shutdown` for why that matters.
+pub struct Endpoint {
+    socket: c_int,
+    id: c_int
+}
+
+impl Endpoint {
+
+    /// Wrap the endpoint id returned by `nn_bind`/`nn_connect` for the
+    /// given socket file descriptor.
+    pub fn new(socket: c_int, id: c_int) -> Endpoint {
+        Endpoint {
+            socket: socket,
+            id: id
+        }
+    }
+
+    /// Shut this endpoint down, closing just this binding/connection while
+    /// leaving the rest of the socket's endpoints untouched.
+    ///
+    /// Calling it is optional, but if called it must be before the `Socket`
+    /// that created it is dropped: the
+    /// `Socket`'s `Drop` closes the underlying file descriptor with
+    /// `nn_close`, and this holds only a copy of that descriptor, not a
+    /// borrow of the `Socket`. Calling `shutdown` after the socket is gone
+    /// means `nn_shutdown` runs on a closed, and possibly by then
+    /// recycled, file descriptor.
+    pub fn shutdown(self) -> NanoResult<()> {
+        let ret = unsafe { libnanomsg::nn_shutdown(self.socket, self.id) };
+
+        if ret == -1 {
+            return Err(NanoError::from_nn_errno(EndpointShutdownError));
+        }
+
+        Ok(())
+    }
+}
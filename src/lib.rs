@@ -9,12 +9,25 @@ extern crate libc;
 extern crate libnanomsg;
 
 pub use result::{NanoResult, NanoError};
+pub use endpoint::Endpoint;
+pub use poll::{PollFd, poll};
+pub use device::{Device, terminate};
 
-use libc::{c_int};
+use libc::{c_int, c_void, size_t};
+use std::c_str::ToCStr;
+use std::cmp;
+use std::io::{IoResult};
 use std::kinds::marker::ContravariantLifetime;
-use result::{SocketInitializationError, SocketBindError};
+use std::mem;
+use std::ptr;
+use std::slice;
+use result::{SocketInitializationError, SocketBindError, SocketConnectError, SendError, ReceiveError};
+use result::{SubscribeError, UnsubscribeError, SetSockOptError, TryAgain};
 
 mod result;
+mod endpoint;
+mod poll;
+mod device;
 
 /// Type-safe protocols that Nanomsg uses. Each socket
 /// is bound to a single protocol that has specific behaviour
@@ -24,7 +37,13 @@ pub enum Protocol {
     Req,
     Rep,
     Push,
-    Pull
+    Pull,
+    Pub,
+    Sub,
+    Pair,
+    Bus,
+    Surveyor,
+    Respondent
 }
 
 /// A type-safe socket wrapper around nanomsg's own socket implementation. This
@@ -58,7 +77,13 @@ impl<'a> Socket<'a> {
             Req => libnanomsg::NN_REQ,
             Rep => libnanomsg::NN_REP,
             Push => libnanomsg::NN_PUSH,
-            Pull => libnanomsg::NN_PULL
+            Pull => libnanomsg::NN_PULL,
+            Pub => libnanomsg::NN_PUB,
+            Sub => libnanomsg::NN_SUB,
+            Pair => libnanomsg::NN_PAIR,
+            Bus => libnanomsg::NN_BUS,
+            Surveyor => libnanomsg::NN_SURVEYOR,
+            Respondent => libnanomsg::NN_RESPONDENT
         };
 
         let socket = unsafe {
@@ -104,21 +129,249 @@ impl<'a> Socket<'a> {
     ///     Err(err) => fail!("Failed to bind socket: {}", err)
     /// }
     /// ```
-    pub fn bind(&mut self, addr: &'a str) -> NanoResult<()> {
-        let ret = unsafe { libnanomsg::nn_bind(self.socket, addr.as_ptr() as *const i8) };
+    pub fn bind(&mut self, addr: &'a str) -> NanoResult<Endpoint> {
+        let c_addr = addr.to_c_str();
+        let endpoint_id = unsafe { libnanomsg::nn_bind(self.socket, c_addr.as_ptr()) };
 
-        if ret == -1 {
+        if endpoint_id == -1 {
             return Err(NanoError::new(format!("Failed to find the socket to the address: {}", addr), SocketBindError));
         }
 
+        Ok(Endpoint::new(self.socket, endpoint_id))
+    }
+
+    /// The opposite of `bind`: rather than listening for connections on
+    /// `addr`, connect out to a socket that is already listening there.
+    /// Like `bind`, a single `Socket` can hold several connected endpoints
+    /// at once, each independently torn down through its `Endpoint`.
+    ///
+    /// Usage:
+    ///
+    /// ```rust
+    /// use nanomsg::{Socket, Push};
+    ///
+    /// let mut socket = match Socket::new(Push) {
+    ///     Ok(socket) => socket,
+    ///     Err(err) => fail!("{}", err)
+    /// };
+    ///
+    /// match socket.connect("ipc:///tmp/pipeline.ipc") {
+    ///     Ok(_) => {},
+    ///     Err(err) => fail!("Failed to connect socket: {}", err)
+    /// }
+    /// ```
+    pub fn connect(&mut self, addr: &'a str) -> NanoResult<Endpoint> {
+        let c_addr = addr.to_c_str();
+        let endpoint_id = unsafe { libnanomsg::nn_connect(self.socket, c_addr.as_ptr()) };
+
+        if endpoint_id == -1 {
+            return Err(NanoError::new(format!("Failed to connect the socket to the address: {}", addr), SocketConnectError));
+        }
+
+        Ok(Endpoint::new(self.socket, endpoint_id))
+    }
+
+    /// Subscribe a `Sub` socket to messages whose leading bytes match
+    /// `topic`. A subscriber receives nothing at all until it subscribes
+    /// to at least one topic; pass an empty slice to subscribe to everything.
+    pub fn subscribe(&mut self, topic: &[u8]) -> NanoResult<()> {
+        let ret = unsafe {
+            libnanomsg::nn_setsockopt(
+                self.socket,
+                libnanomsg::NN_SUB,
+                libnanomsg::NN_SUB_SUBSCRIBE,
+                topic.as_ptr() as *const c_void,
+                topic.len() as size_t)
+        };
+
+        if ret == -1 {
+            return Err(NanoError::from_nn_errno(SubscribeError));
+        }
+
+        Ok(())
+    }
+
+    /// Remove a topic subscription previously added with `subscribe`.
+    pub fn unsubscribe(&mut self, topic: &[u8]) -> NanoResult<()> {
+        let ret = unsafe {
+            libnanomsg::nn_setsockopt(
+                self.socket,
+                libnanomsg::NN_SUB,
+                libnanomsg::NN_SUB_UNSUBSCRIBE,
+                topic.as_ptr() as *const c_void,
+                topic.len() as size_t)
+        };
+
+        if ret == -1 {
+            return Err(NanoError::from_nn_errno(UnsubscribeError));
+        }
+
         Ok(())
     }
+
+    /// How long to block in a blocking send before giving up, in
+    /// milliseconds. A value of `-1` (the default) means wait forever.
+    pub fn set_send_timeout(&mut self, timeout_ms: c_int) -> NanoResult<()> {
+        self.set_option(libnanomsg::NN_SOL_SOCKET, libnanomsg::NN_SNDTIMEO, timeout_ms)
+    }
+
+    /// How long to block in a blocking receive before giving up, in
+    /// milliseconds. A value of `-1` (the default) means wait forever.
+    pub fn set_receive_timeout(&mut self, timeout_ms: c_int) -> NanoResult<()> {
+        self.set_option(libnanomsg::NN_SOL_SOCKET, libnanomsg::NN_RCVTIMEO, timeout_ms)
+    }
+
+    /// Size, in bytes, of the per-socket send buffer.
+    pub fn set_send_buffer_size(&mut self, size_bytes: c_int) -> NanoResult<()> {
+        self.set_option(libnanomsg::NN_SOL_SOCKET, libnanomsg::NN_SNDBUF, size_bytes)
+    }
+
+    /// Size, in bytes, of the per-socket receive buffer.
+    pub fn set_receive_buffer_size(&mut self, size_bytes: c_int) -> NanoResult<()> {
+        self.set_option(libnanomsg::NN_SOL_SOCKET, libnanomsg::NN_RCVBUF, size_bytes)
+    }
+
+    /// How long a `Req` socket waits for a reply before resending the
+    /// request to the next available peer, in milliseconds.
+    pub fn set_request_resend_interval(&mut self, interval_ms: c_int) -> NanoResult<()> {
+        self.set_option(libnanomsg::NN_REQ, libnanomsg::NN_REQ_RESEND_IVL, interval_ms)
+    }
+
+    /// Receive a whole message, blocking until one arrives (or
+    /// `set_receive_timeout` elapses). Unlike `Reader::read`, this never
+    /// truncates: the returned `Vec` is exactly the size of the message
+    /// nanomsg delivered.
+    pub fn recv(&mut self) -> NanoResult<Vec<u8>> {
+        self.recv_with_flags(0)
+    }
+
+    /// Send `buf` as a single message without blocking: if no peer is
+    /// ready to accept it right now, this returns a `TryAgain` error
+    /// instead of blocking, so an event loop built on `poll` can tell
+    /// "not yet" apart from a real failure.
+    pub fn send_nb(&mut self, buf: &[u8]) -> NanoResult<()> {
+        self.send_with_flags(buf, libnanomsg::NN_DONTWAIT)
+    }
+
+    /// Receive a message without blocking: if none is available right
+    /// now, this returns a `TryAgain` error instead of blocking.
+    pub fn recv_nb(&mut self) -> NanoResult<Vec<u8>> {
+        self.recv_with_flags(libnanomsg::NN_DONTWAIT)
+    }
+
+    /// Serialize `value` into a stack buffer and hand it to
+    /// `nn_setsockopt`, the shape every `c_int`-valued socket option shares.
+    fn set_option(&mut self, level: c_int, option: c_int, value: c_int) -> NanoResult<()> {
+        let ret = unsafe {
+            libnanomsg::nn_setsockopt(
+                self.socket,
+                level,
+                option,
+                &value as *const c_int as *const c_void,
+                mem::size_of::<c_int>() as size_t)
+        };
+
+        if ret == -1 {
+            return Err(NanoError::from_nn_errno(SetSockOptError));
+        }
+
+        Ok(())
+    }
+
+    /// Send `buf` as a single nanomsg message, passing `flags` straight
+    /// through to `nn_send` (e.g. `NN_DONTWAIT`).
+    fn send_with_flags(&mut self, buf: &[u8], flags: c_int) -> NanoResult<()> {
+        let ret = unsafe {
+            libnanomsg::nn_send(
+                self.socket,
+                buf.as_ptr() as *const c_void,
+                buf.len() as size_t,
+                flags)
+        };
+
+        if ret < 0 {
+            return Err(NanoError::from_nn_errno(SendError));
+        }
+
+        Ok(())
+    }
+
+    /// Receive a single nanomsg message into a freshly allocated `Vec`,
+    /// passing `flags` straight through to `nn_recv` (e.g. `NN_DONTWAIT`).
+    /// Messages are read using nanomsg's zero-copy `NN_MSG` form: nanomsg
+    /// allocates the buffer, we copy out of it, then free it with
+    /// `nn_freemsg` straight away.
+    fn recv_with_flags(&mut self, flags: c_int) -> NanoResult<Vec<u8>> {
+        let mut msg_ptr: *mut c_void = ptr::null_mut();
+
+        let ret = unsafe {
+            libnanomsg::nn_recv(
+                self.socket,
+                &mut msg_ptr as *mut *mut c_void as *mut c_void,
+                libnanomsg::NN_MSG,
+                flags)
+        };
+
+        if ret < 0 {
+            return Err(NanoError::from_nn_errno(ReceiveError));
+        }
+
+        let len = ret as uint;
+        let message = unsafe {
+            let received = slice::from_raw_buf(&(msg_ptr as *const u8), len);
+            let message = received.to_vec();
+            libnanomsg::nn_freemsg(msg_ptr);
+            message
+        };
+
+        Ok(message)
+    }
+}
+
+impl<'a> Reader for Socket<'a> {
+
+    /// Receive a message from the socket and copy as much of it as fits
+    /// into `buf`, nanomsg-style: one `read` call receives (up to) one
+    /// whole message, it does not fill `buf` from a continuous byte stream.
+    ///
+    /// Two consequences follow from that, and from nanomsg sockets never
+    /// signalling EOF:
+    ///
+    /// * If `buf` is smaller than the message, the remaining bytes are
+    ///   dropped rather than returned on a later call. Prefer `recv`,
+    ///   which returns the whole message with no truncation, unless you
+    ///   already know messages fit in `buf`.
+    /// * `read_to_end`/`read_to_string` will call `read` forever waiting
+    ///   for an EOF that nanomsg message sockets never produce, and so
+    ///   will block past the first message. Don't use them here; call
+    ///   `read` (or `recv`) once per message instead.
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<uint> {
+        match self.recv_with_flags(0) {
+            Ok(message) => {
+                let copied = cmp::min(buf.len(), message.len());
+                slice::bytes::copy_memory(buf, message.slice_to(copied));
+                Ok(copied)
+            },
+            Err(err) => Err(err.to_io_error())
+        }
+    }
+}
+
+impl<'a> Writer for Socket<'a> {
+
+    /// Send `buf` as a single nanomsg message.
+    fn write(&mut self, buf: &[u8]) -> IoResult<()> {
+        self.send_with_flags(buf, 0).map_err(|err| err.to_io_error())
+    }
 }
 
 #[unsafe_destructor]
 impl<'a> Drop for Socket<'a> {
     fn drop(&mut self) {
-        unsafe { libnanomsg::nn_shutdown(self.socket, 0); }
+        // Closing the socket tears down every endpoint still bound or
+        // connected to it, so individual endpoints no longer need to be
+        // shut down by hand before the socket itself goes away.
+        unsafe { libnanomsg::nn_close(self.socket); }
     }
 }
 
@@ -128,6 +381,8 @@ mod tests {
     extern crate debug;
 
     use super::*;
+    use std::io::timer::sleep;
+    use std::time::Duration;
 
     #[test]
     fn initialize_socket() {
@@ -151,4 +406,89 @@ mod tests {
             Err(err) => fail!("{}", err)
         }
     }
+
+    #[test]
+    fn connect_socket() {
+        let mut socket = match Socket::new(Push) {
+            Ok(socket) => socket,
+            Err(err) => fail!("{}", err)
+        };
+
+        match socket.connect("ipc:///tmp/pipeline.ipc") {
+            Ok(_) => {},
+            Err(err) => fail!("{}", err)
+        }
+    }
+
+    #[test]
+    fn subscribe_socket() {
+        let mut socket = match Socket::new(Sub) {
+            Ok(socket) => socket,
+            Err(err) => fail!("{}", err)
+        };
+
+        match socket.subscribe(b"some_topic") {
+            Ok(_) => {},
+            Err(err) => fail!("{}", err)
+        }
+
+        match socket.unsubscribe(b"some_topic") {
+            Ok(_) => {},
+            Err(err) => fail!("{}", err)
+        }
+    }
+
+    #[test]
+    fn send_recv_roundtrip() {
+        let mut pull_socket = match Socket::new(Pull) {
+            Ok(socket) => socket,
+            Err(err) => fail!("{}", err)
+        };
+
+        match pull_socket.bind("ipc:///tmp/roundtrip.ipc") {
+            Ok(_) => {},
+            Err(err) => fail!("Failed to bind socket: {}", err)
+        }
+
+        let mut push_socket = match Socket::new(Push) {
+            Ok(socket) => socket,
+            Err(err) => fail!("{}", err)
+        };
+
+        match push_socket.connect("ipc:///tmp/roundtrip.ipc") {
+            Ok(_) => {},
+            Err(err) => fail!("Failed to connect socket: {}", err)
+        }
+
+        // Give the connect a moment to complete before sending.
+        sleep(Duration::milliseconds(100));
+
+        match push_socket.write(b"hello") {
+            Ok(_) => {},
+            Err(err) => fail!("Failed to send message: {}", err)
+        }
+
+        match pull_socket.recv() {
+            Ok(msg) => assert_eq!(msg.as_slice(), b"hello"),
+            Err(err) => fail!("Failed to receive message: {}", err)
+        }
+    }
+
+    #[test]
+    fn recv_nb_reports_try_again_with_nothing_pending() {
+        let mut socket = match Socket::new(Pull) {
+            Ok(socket) => socket,
+            Err(err) => fail!("{}", err)
+        };
+
+        match socket.bind("ipc:///tmp/recv_nb.ipc") {
+            Ok(_) => {},
+            Err(err) => fail!("Failed to bind socket: {}", err)
+        }
+
+        match socket.recv_nb() {
+            Ok(msg) => fail!("expected no message to be pending, got {}", msg),
+            Err(err) => assert_eq!(err.kind, TryAgain)
+        }
+    }
 }
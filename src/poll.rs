@@ -0,0 +1,58 @@
+use libc::{c_int, c_short};
+
+use libnanomsg;
+use result::{NanoResult, NanoError};
+use result::{PollError};
+use Socket;
+
+/// One entry in a call to `poll`: which socket to watch, the bitmask of
+/// `NN_POLLIN`/`NN_POLLOUT` events to watch for, and the bitmask of events
+/// that were actually ready once `poll` returns.
+pub struct PollFd {
+    pub socket_fd: c_int,
+    pub events: c_short,
+    pub revents: c_short
+}
+
+impl PollFd {
+
+    /// Watch `socket` for the given bitmask of events.
+    pub fn new(socket: &Socket, events: c_short) -> PollFd {
+        PollFd {
+            socket_fd: socket.socket,
+            events: events,
+            revents: 0
+        }
+    }
+}
+
+/// Block until at least one socket in `fds` becomes ready or `timeout_ms`
+/// elapses (`-1` to wait forever), writing each descriptor's `revents`
+/// back into its `PollFd`. Returns the number of ready descriptors, which
+/// is `0` on timeout.
+///
+/// This is what lets a single task drive an event loop over many sockets
+/// instead of dedicating one task to each.
+pub fn poll(fds: &mut [PollFd], timeout_ms: int) -> NanoResult<uint> {
+    let mut raw_fds: Vec<libnanomsg::nn_pollfd> = fds.iter().map(|fd| {
+        libnanomsg::nn_pollfd {
+            fd: fd.socket_fd,
+            events: fd.events,
+            revents: 0
+        }
+    }).collect();
+
+    let ret = unsafe {
+        libnanomsg::nn_poll(raw_fds.as_mut_ptr(), raw_fds.len() as c_int, timeout_ms as c_int)
+    };
+
+    if ret < 0 {
+        return Err(NanoError::from_nn_errno(PollError));
+    }
+
+    for (fd, raw_fd) in fds.iter_mut().zip(raw_fds.iter()) {
+        fd.revents = raw_fd.revents;
+    }
+
+    Ok(ret as uint)
+}
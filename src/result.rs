@@ -0,0 +1,78 @@
+use std::fmt;
+use std::io::{IoError, OtherIoError};
+
+use libc::EAGAIN;
+use libnanomsg;
+
+/// The result of any operation that can fail with a nanomsg-specific error.
+pub type NanoResult<T> = Result<T, NanoError>;
+
+/// Coarse-grained classification of what went wrong, so callers can
+/// `match` on the kind of failure instead of parsing the description.
+#[deriving(Show, PartialEq)]
+pub enum NanoErrorKind {
+    SocketInitializationError,
+    SocketBindError,
+    SocketConnectError,
+    EndpointShutdownError,
+    SendError,
+    ReceiveError,
+    SubscribeError,
+    UnsubscribeError,
+    SetSockOptError,
+    PollError,
+    DeviceError,
+    TryAgain
+}
+
+/// An error originating from a call into the underlying nanomsg library.
+pub struct NanoError {
+    pub description: String,
+    pub kind: NanoErrorKind
+}
+
+impl NanoError {
+
+    /// Build a new error from anything that can be turned into a string
+    /// and the kind of failure it represents.
+    pub fn new<T: fmt::Show>(description: T, kind: NanoErrorKind) -> NanoError {
+        NanoError {
+            description: description.to_string(),
+            kind: kind
+        }
+    }
+
+    /// Build an error from whatever `nn_errno()` currently reports, which is
+    /// what every `nn_*` call leaves behind on a `-1` return. This is the
+    /// usual way to construct a `NanoError` once you already know which
+    /// operation failed.
+    ///
+    /// `EAGAIN` is special-cased to `TryAgain` regardless of `kind`, since
+    /// it means "no message yet", not a real failure of the operation.
+    pub fn from_nn_errno(kind: NanoErrorKind) -> NanoError {
+        let errno = unsafe { libnanomsg::nn_errno() };
+        let kind = if errno == EAGAIN { TryAgain } else { kind };
+        let description = unsafe {
+            let c_str = libnanomsg::nn_strerror(errno);
+            String::from_raw_buf(c_str as *const u8)
+        };
+
+        NanoError::new(description, kind)
+    }
+
+    /// Adapt this error to the std `IoError` type expected by the `Reader`
+    /// and `Writer` trait implementations.
+    pub fn to_io_error(self) -> IoError {
+        IoError {
+            kind: OtherIoError,
+            desc: "nanomsg error",
+            detail: Some(self.description)
+        }
+    }
+}
+
+impl fmt::Show for NanoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (kind: {})", self.description, self.kind)
+    }
+}